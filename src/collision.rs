@@ -0,0 +1,119 @@
+use anyhow::anyhow;
+use anyhow::Result;
+use log::debug;
+use rppal::gpio::Gpio;
+use rppal::gpio::InputPin;
+use rppal::gpio::Level;
+use rppal::i2c::I2c;
+
+use crate::config::CollisionConfig;
+
+/// The abstraction of an anti-collision / stall safety sensor.
+///
+/// Mirrors [`DistanceSensor`](crate::sensor::DistanceSensor) so the motor
+/// loops can treat any accelerometer-backed implementation the same way.
+pub(crate) trait CollisionSensor {
+    /// Returns whether a shock event has latched since the last poll.
+    fn poll_shock(&mut self) -> Result<bool>;
+}
+
+/// An LIS3DH-class accelerometer wired over I2C, used to detect a sudden
+/// jerk (the desk hitting an obstacle) via its high-pass filtered interrupt.
+#[derive(Debug)]
+pub(crate) struct Lis3dh {
+    i2c: I2c,
+    interrupt_pin: InputPin,
+}
+
+// LIS3DH register addresses relevant to the high-pass filtered interrupt
+// generator, per the datasheet.
+const REG_CTRL_REG2: u8 = 0x21;
+const REG_CTRL_REG3: u8 = 0x22;
+const REG_CTRL_REG5: u8 = 0x24;
+const REG_INT1_CFG: u8 = 0x30;
+const REG_INT1_SRC: u8 = 0x31;
+const REG_INT1_THS: u8 = 0x32;
+const REG_INT1_DURATION: u8 = 0x33;
+
+// LIR_INT1: latch interrupt request on INT1_SRC, cleared only by reading
+// INT1_SRC. Without this the line can fall again before we poll it, and a
+// shock shorter than our poll interval would be missed.
+const LIR_INT1: u8 = 0b0000_1000;
+
+// IA: at least one axis has exceeded its interrupt threshold.
+const INT1_SRC_IA: u8 = 0b0100_0000;
+
+impl Lis3dh {
+    /// Creates a new [Lis3dh] instance and arms the high-pass filtered
+    /// interrupt with the configured threshold and duration.
+    pub(crate) fn new(config: CollisionConfig) -> Result<Self> {
+        let gpio = Gpio::new().expect("gpio to be available");
+        let interrupt_pin = gpio
+            .get(config.interrupt_pin)
+            .expect("interrupt pin be available")
+            .into_input_pulldown();
+        let mut i2c = I2c::with_bus(config.i2c_bus)?;
+        i2c.set_slave_address(config.i2c_address as u16)?;
+
+        // Enable the high-pass filter for interrupt generator 1.
+        i2c.block_write(REG_CTRL_REG2, &[0b0000_0001])?;
+        // Route interrupt generator 1 to the INT1 pin.
+        i2c.block_write(REG_CTRL_REG3, &[0b0100_0000])?;
+        // Latch the interrupt until INT1_SRC is read, so a shock shorter
+        // than our poll interval isn't missed between `poll_shock()` calls.
+        i2c.block_write(REG_CTRL_REG5, &[LIR_INT1])?;
+        i2c.block_write(REG_INT1_THS, &[config.shock_threshold])?;
+        i2c.block_write(REG_INT1_DURATION, &[config.shock_duration])?;
+        // Latch on high event on any axis.
+        i2c.block_write(REG_INT1_CFG, &[0b0010_1010])?;
+
+        Ok(Self {
+            i2c,
+            interrupt_pin,
+        })
+    }
+
+    /// Whether `INT1_SRC`'s IA bit indicates a shock on any axis. Doesn't
+    /// touch hardware, so it's exercised directly in tests.
+    fn is_interrupt_active(int1_src: u8) -> bool {
+        int1_src & INT1_SRC_IA != 0
+    }
+}
+
+impl CollisionSensor for Lis3dh {
+    /// Checks whether the interrupt line has latched a shock event, clearing
+    /// the latch on the accelerometer afterwards.
+    fn poll_shock(&mut self) -> Result<bool> {
+        if self.interrupt_pin.read() != Level::High {
+            return Ok(false);
+        }
+        debug!("Collision sensor interrupt pin is high, checking INT1_SRC");
+        // Reading INT1_SRC clears the latched interrupt on the accelerometer.
+        let mut src = [0u8];
+        self.i2c
+            .block_read(REG_INT1_SRC, &mut src)
+            .map_err(|e| anyhow!("failed to read and clear collision interrupt: {e}"))?;
+        let shock = Self::is_interrupt_active(src[0]);
+        if shock {
+            debug!("Collision sensor detected a shock");
+        }
+        Ok(shock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_interrupt_active_detects_the_ia_bit() {
+        assert!(Lis3dh::is_interrupt_active(0b0100_0000));
+        assert!(Lis3dh::is_interrupt_active(0b0100_0001));
+    }
+
+    #[test]
+    fn is_interrupt_active_ignores_other_bits() {
+        assert!(!Lis3dh::is_interrupt_active(0b0000_0000));
+        assert!(!Lis3dh::is_interrupt_active(0b1011_1111));
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::motor::MotorConfig;
+use crate::primitives::Centimeter;
+
+/// Top-level configuration, typically loaded from a TOML file at startup.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Config {
+    pub sensor: SensorConfig,
+    pub motor: MotorConfig,
+    pub table: TableConfig,
+    pub collision: CollisionConfig,
+    pub sampling: SamplingConfig,
+}
+
+/// Configuration for the [`HCSR04`](crate::sensor::HCSR04) distance sensor.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SensorConfig {
+    pub calibration_file: PathBuf,
+    pub trigger_pin: u8,
+    pub echo_pin: u8,
+    /// The number of measurements to take in a burst before filtering and
+    /// averaging them into a single reading.
+    pub measurement_burst: u8,
+    /// Maximum deviation from the median burst sample, in centimeter
+    /// equivalent, before a sample is rejected as a spike.
+    pub burst_rejection_threshold_cm: f32,
+    /// Ambient temperature in Celsius, used to derive the speed of sound for
+    /// the temperature-compensated distance model. Leave unset to fall back
+    /// to the two-point calibration interpolation.
+    #[serde(default)]
+    pub ambient_temperature_celsius: Option<f32>,
+    /// Ambient relative humidity in percent, refining the speed-of-sound
+    /// calculation when it's also set.
+    #[serde(default)]
+    pub ambient_relative_humidity_percent: Option<f32>,
+}
+
+/// Configuration for the [`StandingDesk`](crate::table::StandingDesk).
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TableConfig {
+    pub standing_height: Centimeter,
+    pub sitting_height: Centimeter,
+    pub max_table_height: Centimeter,
+    pub min_table_height: Centimeter,
+    /// Number of recent height readings a move's [`StallGuard`](crate::stall::StallGuard)
+    /// compares against to detect stalled progress. `0` disables stall
+    /// detection while still enforcing `move_timeout`.
+    pub stall_window_size: usize,
+    /// Minimum height change, in centimeters, expected across
+    /// `stall_window_size` readings before a move is considered stalled.
+    pub stall_min_progress_cm: u8,
+    /// How long a move may show less than `stall_min_progress_cm` progress
+    /// before it's aborted as stalled.
+    pub stall_timeout: Duration,
+    /// Hard ceiling on how long a single move may run, regardless of
+    /// progress.
+    pub move_timeout: Duration,
+}
+
+/// Configuration for the optional [`Lis3dh`](crate::collision::Lis3dh)
+/// anti-collision accelerometer.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct CollisionConfig {
+    pub enabled: bool,
+    pub i2c_bus: u8,
+    pub i2c_address: u8,
+    pub interrupt_pin: u8,
+    /// High-pass filtered interrupt threshold, in the accelerometer's own
+    /// register units (see the LIS3DH datasheet for the INT1_THS scale).
+    pub shock_threshold: u8,
+    /// How long the acceleration must exceed `shock_threshold` before the
+    /// interrupt latches, in the accelerometer's own duration units.
+    pub shock_duration: u8,
+}
+
+/// Configuration for the [`BackgroundSampler`](crate::sampling::BackgroundSampler)
+/// that feeds the height ring buffer consumed by the movement loops.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SamplingConfig {
+    pub enabled: bool,
+    pub buffer_depth: usize,
+    pub sample_rate_hz: f32,
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::primitives::Centimeter;
+
+/// A single timestamped height reading.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeightSample {
+    pub height: Centimeter,
+    pub at: Instant,
+}
+
+/// A fixed-capacity ring buffer of recent height readings, fed by the
+/// background sampling thread and consumed by the movement loops and other
+/// features (logging, diagnostics) that want a short history.
+#[derive(Debug)]
+pub(crate) struct HeightHistory {
+    samples: VecDeque<HeightSample>,
+    capacity: usize,
+    // Number of readings evicted because the buffer was already full.
+    overflow_count: u64,
+}
+
+impl HeightHistory {
+    /// Creates a new, empty history with room for `capacity` samples. A
+    /// `capacity` of `0` disables history entirely; `push` becomes a no-op.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            overflow_count: 0,
+        }
+    }
+
+    /// Pushes a new reading, evicting the oldest one if the buffer is
+    /// already full. Does nothing if `capacity` is `0`.
+    pub(crate) fn push(
+        &mut self,
+        height: Centimeter,
+        at: Instant,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+            self.overflow_count += 1;
+        }
+        self.samples.push_back(HeightSample { height, at });
+    }
+
+    /// Returns the most recent reading, if any.
+    pub(crate) fn latest(&self) -> Option<HeightSample> {
+        self.samples.back().copied()
+    }
+
+    /// Returns the readings currently held, oldest first.
+    pub(crate) fn samples(&self) -> impl Iterator<Item = &HeightSample> {
+        self.samples.iter()
+    }
+
+    /// The number of readings evicted because the buffer was full.
+    pub(crate) fn overflow_count(&self) -> u64 {
+        self.overflow_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_keeps_readings_up_to_capacity() {
+        let mut history = HeightHistory::new(2);
+        history.push(Centimeter(10), Instant::now());
+        history.push(Centimeter(20), Instant::now());
+        assert_eq!(history.samples().count(), 2);
+        assert_eq!(history.overflow_count(), 0);
+    }
+
+    #[test]
+    fn push_evicts_the_oldest_reading_once_full() {
+        let mut history = HeightHistory::new(2);
+        history.push(Centimeter(10), Instant::now());
+        history.push(Centimeter(20), Instant::now());
+        history.push(Centimeter(30), Instant::now());
+        let heights: Vec<Centimeter> = history.samples().map(|sample| sample.height).collect();
+        assert_eq!(heights, vec![Centimeter(20), Centimeter(30)]);
+        assert_eq!(history.overflow_count(), 1);
+    }
+
+    #[test]
+    fn latest_returns_the_most_recently_pushed_reading() {
+        let mut history = HeightHistory::new(2);
+        assert!(history.latest().is_none());
+        history.push(Centimeter(10), Instant::now());
+        history.push(Centimeter(20), Instant::now());
+        assert_eq!(history.latest().unwrap().height, Centimeter(20));
+    }
+
+    #[test]
+    fn zero_capacity_never_stores_a_reading() {
+        let mut history = HeightHistory::new(0);
+        history.push(Centimeter(10), Instant::now());
+        history.push(Centimeter(20), Instant::now());
+        assert_eq!(history.samples().count(), 0);
+        assert!(history.latest().is_none());
+        assert_eq!(history.overflow_count(), 0);
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::warn;
+
+use crate::height_history::HeightHistory;
+use crate::primitives::Centimeter;
+use crate::sensor::DistanceSensor;
+
+/// Continuously samples a [`DistanceSensor`] on a dedicated thread and feeds
+/// readings into a shared [`HeightHistory`], so the movement loops can read
+/// the most recent height without blocking on a fresh measurement.
+#[derive(Debug)]
+pub(crate) struct BackgroundSampler {
+    history: Arc<Mutex<HeightHistory>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundSampler {
+    /// Spawns the sampling thread, filling a ring buffer of `buffer_depth`
+    /// readings at `sample_rate_hz`.
+    pub(crate) fn spawn<S>(
+        sensor: Arc<Mutex<S>>,
+        buffer_depth: usize,
+        sample_rate_hz: f32,
+    ) -> Self
+    where
+        S: DistanceSensor + Send + 'static,
+    {
+        let history = Arc::new(Mutex::new(HeightHistory::new(buffer_depth)));
+        let running = Arc::new(AtomicBool::new(true));
+        let period = Duration::from_secs_f32(1.0 / sample_rate_hz);
+
+        let thread_history = Arc::clone(&history);
+        let thread_running = Arc::clone(&running);
+        let handle = thread::spawn(move || {
+            let mut last_overflow_count = 0;
+            while thread_running.load(Ordering::Relaxed) {
+                let started_at = Instant::now();
+                match sensor
+                    .lock()
+                    .expect("sensor mutex must not be poisoned")
+                    .current_height()
+                {
+                    Ok(height) => {
+                        let mut history = thread_history
+                            .lock()
+                            .expect("history mutex must not be poisoned");
+                        history.push(height, Instant::now());
+                        let overflow_count = history.overflow_count();
+                        if overflow_count > last_overflow_count {
+                            warn!(
+                                "Height ring buffer overflowed, {} readings evicted so far",
+                                overflow_count
+                            );
+                            last_overflow_count = overflow_count;
+                        }
+                    }
+                    Err(error) => warn!("Background sampling measurement failed: {error}"),
+                }
+                let elapsed = started_at.elapsed();
+                if elapsed < period {
+                    thread::sleep(period - elapsed);
+                }
+            }
+        });
+
+        Self {
+            history,
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    /// Returns the most recent height reading, or `None` if the buffer
+    /// hasn't been filled with a first sample yet.
+    pub(crate) fn latest_height(&self) -> Option<Centimeter> {
+        self.history
+            .lock()
+            .expect("history mutex must not be poisoned")
+            .latest()
+            .map(|sample| sample.height)
+    }
+}
+
+impl Drop for BackgroundSampler {
+    /// Signals the sampling thread to stop and waits for it to exit.
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -54,6 +54,15 @@ pub(crate) struct HCSR04 {
     // This is a u8 because it doesn't make sense to take more than 256 measurements in burst
     // as that would equal to around 7s of measurement time.
     measurement_burst: u8,
+    // Ambient temperature in Celsius, if known. When set, distance is derived
+    // from the speed of sound instead of the two-point calibration interpolation.
+    ambient_temperature_celsius: Option<f32>,
+    // Ambient relative humidity in percent, if known. Only used to refine the
+    // speed of sound when `ambient_temperature_celsius` is also set.
+    ambient_relative_humidity_percent: Option<f32>,
+    // Maximum deviation from the median burst sample, in centimeter
+    // equivalent, before a sample is rejected as a spike.
+    burst_rejection_threshold_cm: f32,
 }
 
 /// A struct for storing the calibration data for the sensor.
@@ -67,6 +76,12 @@ pub(crate) struct SensorCalibrationData {
     pub max_height: Centimeter,
     // The duration of the echo in seconds at max height
     pub max_height_echo_secs: f32,
+    // The height of the sensor mount, on the same scale as the desk heights:
+    // the calibrated desk height plus the echo distance measured at the single
+    // calibration point used for the temperature-compensated distance model.
+    // `None` until a calibration run records it.
+    #[serde(default)]
+    pub mount_reference_height: Option<Centimeter>,
 }
 
 impl SensorCalibrationData {
@@ -96,7 +111,7 @@ impl HCSR04 {
         echo_pin
             .set_interrupt(Trigger::Both)
             .expect("must be able to set echo interrupt trigger");
-        let measurement_burst = 3;
+        let measurement_burst = config.measurement_burst;
         Self {
             calibration_file_path,
             calibration_data,
@@ -107,11 +122,68 @@ impl HCSR04 {
             echo_pin,
             measurement_buffer: Vec::with_capacity(measurement_burst as usize),
             measurement_burst,
+            ambient_temperature_celsius: config.ambient_temperature_celsius,
+            ambient_relative_humidity_percent: config.ambient_relative_humidity_percent,
+            burst_rejection_threshold_cm: config.burst_rejection_threshold_cm,
         }
     }
 
-    /// Performs multiple echo measurements and takes the average for a less
-    /// noisy signal.
+    /// Updates the ambient temperature used for the speed-of-sound distance
+    /// model, letting the controller refresh it without a full recalibration.
+    pub(crate) fn set_ambient_temperature(
+        &mut self,
+        temperature_celsius: f32,
+    ) {
+        debug!("Setting ambient temperature to {temperature_celsius}°C");
+        self.ambient_temperature_celsius = Some(temperature_celsius);
+    }
+
+    /// Updates the ambient relative humidity used to refine the speed-of-sound
+    /// distance model.
+    pub(crate) fn set_ambient_relative_humidity(
+        &mut self,
+        relative_humidity_percent: f32,
+    ) {
+        debug!("Setting ambient relative humidity to {relative_humidity_percent}%");
+        self.ambient_relative_humidity_percent = Some(relative_humidity_percent);
+    }
+
+    /// Computes the speed of sound in m/s from the given ambient conditions,
+    /// or `None` if no temperature is known. Doesn't touch hardware, so it's
+    /// exercised directly in tests.
+    ///
+    /// Uses `c = 331.3 + 0.606 * T`, with an optional `+ 0.0124 * RH%` term
+    /// when relative humidity is also known.
+    fn speed_of_sound_m_s(
+        ambient_temperature_celsius: Option<f32>,
+        ambient_relative_humidity_percent: Option<f32>,
+    ) -> Option<f32> {
+        let temperature = ambient_temperature_celsius?;
+        let mut speed = 331.3 + 0.606 * temperature;
+        if let Some(humidity) = ambient_relative_humidity_percent {
+            speed += 0.0124 * humidity;
+        }
+        Some(speed)
+    }
+
+    /// Derives a height from an echo duration using the temperature-
+    /// compensated speed of sound, or `None` if `speed_of_sound_m_s` or
+    /// `mount_reference_height` aren't available. Doesn't touch hardware, so
+    /// it's exercised directly in tests.
+    fn height_from_speed_of_sound(
+        echo_duration_secs: f32,
+        speed_of_sound_m_s: Option<f32>,
+        mount_reference_height: Option<Centimeter>,
+    ) -> Option<Centimeter> {
+        let speed_of_sound = speed_of_sound_m_s?;
+        let mount_reference_height = mount_reference_height?;
+        let distance_cm = echo_duration_secs * speed_of_sound * 100.0 / 2.0;
+        let height = mount_reference_height.into_inner() as f32 - distance_cm;
+        Some(Centimeter(height.round() as u8))
+    }
+
+    /// Performs multiple echo measurements and takes the average of the
+    /// samples that aren't rejected as spikes for a less noisy signal.
     fn measure_burst_echo_duration(&mut self) -> Result<Duration> {
         self.measurement_buffer.clear();
         for _ in 0..self.measurement_burst {
@@ -119,12 +191,53 @@ impl HCSR04 {
             self.measurement_buffer.push(echo);
             sleep(Duration::from_millis(30));
         }
+        let rejection_delta = self.rejection_delta_duration();
         let average_burst_echo_duration =
-            self.measurement_buffer.iter().sum::<Duration>() / self.measurement_burst as u32;
+            Self::average_with_spike_rejection(&mut self.measurement_buffer, rejection_delta)?;
         debug!("average_burst_echo_duration: {average_burst_echo_duration:?}");
         Ok(average_burst_echo_duration)
     }
 
+    /// Converts `burst_rejection_threshold_cm` into an echo-duration delta,
+    /// using the current speed of sound estimate (falling back to the speed
+    /// at 20°C when no ambient temperature is known).
+    fn rejection_delta_duration(&self) -> Duration {
+        let speed_of_sound_m_s = Self::speed_of_sound_m_s(
+            self.ambient_temperature_celsius,
+            self.ambient_relative_humidity_percent,
+        )
+        .unwrap_or(343.42);
+        let rejection_delta_secs =
+            self.burst_rejection_threshold_cm / 100.0 * 2.0 / speed_of_sound_m_s;
+        Duration::from_secs_f32(rejection_delta_secs)
+    }
+
+    /// Averages the samples in `durations` that fall within `rejection_delta`
+    /// of the median, discarding the rest as spikes. Doesn't touch hardware,
+    /// so it's exercised directly in tests.
+    ///
+    /// # Errors
+    /// Errors if `durations` is empty.
+    fn average_with_spike_rejection(
+        durations: &mut [Duration],
+        rejection_delta: Duration,
+    ) -> Result<Duration> {
+        if durations.is_empty() {
+            return Err(anyhow!("cannot average an empty burst of measurements"));
+        }
+        durations.sort_unstable();
+        let median = durations[durations.len() / 2];
+        let survivors: Vec<Duration> = durations
+            .iter()
+            .copied()
+            .filter(|&duration| {
+                duration.abs_diff(median) <= rejection_delta
+            })
+            .collect();
+        let survivor_count = survivors.len() as u32;
+        Ok(survivors.into_iter().sum::<Duration>() / survivor_count)
+    }
+
     /// Measures the time it takes for the sensor to send and receive an
     /// acoustic echo.
     ///
@@ -181,8 +294,25 @@ impl HCSR04 {
 impl DistanceSensor for HCSR04 {
     /// Computes the sensor's current height, taking the calibration data into
     /// account.
+    ///
+    /// Prefers the temperature-compensated speed-of-sound model when ambient
+    /// conditions and a mount reference point are configured, since it does
+    /// not drift as the room temperature changes. Falls back to the
+    /// two-point calibration interpolation otherwise.
     fn current_height(&mut self) -> Result<Centimeter> {
         let echo_duration = self.measure_burst_echo_duration()?.as_secs_f32();
+        let speed_of_sound = Self::speed_of_sound_m_s(
+            self.ambient_temperature_celsius,
+            self.ambient_relative_humidity_percent,
+        );
+        if let Some(height) = Self::height_from_speed_of_sound(
+            echo_duration,
+            speed_of_sound,
+            self.calibration_data.mount_reference_height,
+        ) {
+            debug!("Current height (speed of sound) is {height:?}");
+            return Ok(height);
+        }
         // We're interpolating the height from our calibration parameters
         let min_height_calibration_echo = self.calibration_data.min_height_echo_secs;
         let max_height_calibration_echo = self.calibration_data.max_height_echo_secs;
@@ -207,6 +337,16 @@ impl DistanceSensor for HCSR04 {
         debug!("Min height echo duration: {echo_duration:?}");
         self.calibration_data.min_height_echo_secs = echo_duration.as_secs_f32();
         self.calibration_data.min_height = height;
+        let speed_of_sound = Self::speed_of_sound_m_s(
+            self.ambient_temperature_celsius,
+            self.ambient_relative_humidity_percent,
+        );
+        if let Some(speed_of_sound) = speed_of_sound {
+            let distance_cm = echo_duration.as_secs_f32() * speed_of_sound * 100.0 / 2.0;
+            let mount_reference_height = height.into_inner() as f32 + distance_cm;
+            self.calibration_data.mount_reference_height =
+                Some(Centimeter(mount_reference_height.round() as u8));
+        }
         Ok(())
     }
 
@@ -231,3 +371,96 @@ impl DistanceSensor for HCSR04 {
         &self.calibration_data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_of_sound_m_s_is_none_without_a_temperature() {
+        assert_eq!(HCSR04::speed_of_sound_m_s(None, None), None);
+        assert_eq!(HCSR04::speed_of_sound_m_s(None, Some(50.0)), None);
+    }
+
+    #[test]
+    fn speed_of_sound_m_s_uses_temperature_only() {
+        let speed = HCSR04::speed_of_sound_m_s(Some(20.0), None).unwrap();
+        assert!((speed - 343.42).abs() < 0.01);
+    }
+
+    #[test]
+    fn speed_of_sound_m_s_adds_humidity_term() {
+        let speed = HCSR04::speed_of_sound_m_s(Some(20.0), Some(50.0)).unwrap();
+        assert!((speed - 344.04).abs() < 0.01);
+    }
+
+    #[test]
+    fn height_from_speed_of_sound_is_none_without_speed_of_sound() {
+        let height = HCSR04::height_from_speed_of_sound(0.01, None, Some(Centimeter(100)));
+        assert_eq!(height, None);
+    }
+
+    #[test]
+    fn height_from_speed_of_sound_is_none_without_mount_reference() {
+        let height = HCSR04::height_from_speed_of_sound(0.01, Some(343.0), None);
+        assert_eq!(height, None);
+    }
+
+    #[test]
+    fn height_from_speed_of_sound_derives_height_below_the_mount() {
+        // 0.01s echo at 343m/s is a 3.43m round trip, i.e. ~171.5cm distance.
+        let height =
+            HCSR04::height_from_speed_of_sound(0.01, Some(343.0), Some(Centimeter(200)))
+                .unwrap();
+        assert_eq!(height, Centimeter(29));
+    }
+
+    #[test]
+    fn average_with_spike_rejection_ignores_outliers() {
+        let mut durations = vec![
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+            Duration::from_millis(31),
+        ];
+        let average =
+            HCSR04::average_with_spike_rejection(&mut durations, Duration::from_millis(1))
+                .unwrap();
+        assert_eq!(average, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn average_with_spike_rejection_keeps_all_samples_within_threshold() {
+        let mut durations = vec![
+            Duration::from_millis(10),
+            Duration::from_millis(11),
+            Duration::from_millis(9),
+        ];
+        let average =
+            HCSR04::average_with_spike_rejection(&mut durations, Duration::from_millis(2))
+                .unwrap();
+        assert_eq!(average, Duration::from_nanos(10_000_000));
+    }
+
+    #[test]
+    fn average_with_spike_rejection_errors_on_empty_burst() {
+        let mut durations: Vec<Duration> = vec![];
+        let result =
+            HCSR04::average_with_spike_rejection(&mut durations, Duration::from_millis(1));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn average_with_spike_rejection_falls_back_to_the_median_alone() {
+        // A threshold of zero only keeps samples equal to the median, which
+        // always includes the median sample itself.
+        let mut durations = vec![
+            Duration::from_millis(5),
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+        ];
+        let average =
+            HCSR04::average_with_spike_rejection(&mut durations, Duration::from_millis(0))
+                .unwrap();
+        assert_eq!(average, Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,161 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::height_history::HeightHistory;
+use crate::primitives::Centimeter;
+
+/// Detects a stalled or runaway movement over the course of a single motor
+/// move, using a short sliding window of height readings.
+///
+/// A jammed motor, a disconnected sensor, or a desk that can't reach its
+/// target would otherwise spin the move loops forever.
+#[derive(Debug)]
+pub(crate) struct StallGuard {
+    started_at: Instant,
+    move_timeout: Duration,
+    // Reuses the same ring buffer the background sampler feeds, just scoped
+    // to this one move and sized to `window_size`.
+    window: HeightHistory,
+    window_size: usize,
+    min_progress_cm: u8,
+    stall_timeout: Duration,
+    // Set the first time the window shows insufficient progress; cleared
+    // again as soon as progress resumes.
+    stalled_since: Option<Instant>,
+}
+
+impl StallGuard {
+    /// Creates a new guard, starting the absolute move timeout now.
+    ///
+    /// A `window_size` of `0` disables stall detection entirely; only the
+    /// absolute `move_timeout` still applies.
+    pub(crate) fn new(
+        window_size: usize,
+        min_progress_cm: u8,
+        stall_timeout: Duration,
+        move_timeout: Duration,
+    ) -> Self {
+        Self {
+            started_at: Instant::now(),
+            move_timeout,
+            window: HeightHistory::new(window_size),
+            window_size,
+            min_progress_cm,
+            stall_timeout,
+            stalled_since: None,
+        }
+    }
+
+    /// Records a new height reading and errors if the move has either timed
+    /// out entirely or stalled for longer than `stall_timeout`.
+    pub(crate) fn record(
+        &mut self,
+        height: Centimeter,
+    ) -> Result<()> {
+        let now = Instant::now();
+        if now.duration_since(self.started_at) > self.move_timeout {
+            return Err(anyhow!(
+                "move aborted, exceeded move timeout of {:?}",
+                self.move_timeout
+            ));
+        }
+        if self.window_size == 0 {
+            return Ok(());
+        }
+
+        // Only compare once the window actually holds `window_size`
+        // readings — otherwise we'd be comparing against a window that's
+        // still filling up, which would flag noise as a stall.
+        if self.window.samples().count() == self.window_size {
+            // Compare against the window as it stood before this reading, so a
+            // `window_size` of 1 still compares consecutive readings instead of
+            // a reading against itself.
+            let oldest = self
+                .window
+                .samples()
+                .next()
+                .expect("window is full, so it must have an oldest sample");
+            let progress = height.into_inner().abs_diff(oldest.height.into_inner());
+            if progress < self.min_progress_cm {
+                let stalled_since = *self.stalled_since.get_or_insert(now);
+                if now.duration_since(stalled_since) > self.stall_timeout {
+                    return Err(anyhow!(
+                        "move aborted, height made less than {}cm progress over the last {} readings for {:?}",
+                        self.min_progress_cm,
+                        self.window_size,
+                        self.stall_timeout
+                    ));
+                }
+            } else {
+                self.stalled_since = None;
+            }
+        }
+        self.window.push(height, now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    #[test]
+    fn record_errors_once_move_timeout_elapses() {
+        let mut guard = StallGuard::new(3, 1, Duration::from_secs(60), Duration::from_millis(20));
+        assert!(guard.record(Centimeter(100)).is_ok());
+        sleep(Duration::from_millis(30));
+        let result = guard.record(Centimeter(100));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn record_errors_once_stalled_for_longer_than_stall_timeout() {
+        let mut guard = StallGuard::new(1, 2, Duration::from_millis(20), Duration::from_secs(60));
+        // First reading just seeds the window, there's nothing to compare yet.
+        assert!(guard.record(Centimeter(100)).is_ok());
+        // Second reading is within the no-progress threshold, starting the
+        // stall timer.
+        assert!(guard.record(Centimeter(100)).is_ok());
+        sleep(Duration::from_millis(30));
+        let result = guard.record(Centimeter(100));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn record_does_not_stall_while_height_keeps_progressing() {
+        let mut guard = StallGuard::new(1, 2, Duration::from_millis(20), Duration::from_secs(60));
+        // Each step moves further than `min_progress_cm`, so the stall timer
+        // should keep resetting instead of ever tripping.
+        for height in (100..130).step_by(3) {
+            sleep(Duration::from_millis(25));
+            assert!(guard.record(Centimeter(height)).is_ok());
+        }
+    }
+
+    #[test]
+    fn record_with_zero_window_size_only_checks_move_timeout() {
+        let mut guard = StallGuard::new(0, 1, Duration::from_millis(1), Duration::from_secs(60));
+        for _ in 0..5 {
+            sleep(Duration::from_millis(5));
+            assert!(guard.record(Centimeter(100)).is_ok());
+        }
+    }
+
+    #[test]
+    fn record_does_not_compare_until_the_window_is_full() {
+        // With a window of 3 and strictly increasing-by-1 readings, the
+        // comparison must not fire before the window actually holds 3
+        // samples — otherwise it would compare against too few readings
+        // and misreport noise as a stall.
+        let mut guard = StallGuard::new(3, 5, Duration::from_millis(1), Duration::from_secs(60));
+        for height in 100..103 {
+            let result = guard.record(Centimeter(height));
+            assert!(result.is_ok(), "window should not be full yet");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -5,38 +7,160 @@ use anyhow::anyhow;
 use anyhow::Result;
 use log::debug;
 use log::info;
+use log::warn;
 
+use crate::collision::CollisionSensor;
+use crate::collision::Lis3dh;
 use crate::config::Config;
 use crate::config::TableConfig;
 use crate::motor::DeskMotor;
 use crate::motor::Motor;
 use crate::movement::Movement;
 use crate::primitives::Centimeter;
+use crate::sampling::BackgroundSampler;
 use crate::sensor::DistanceSensor;
 use crate::sensor::HCSR04;
+use crate::stall::StallGuard;
+
+/// How long the motor reverses for to relieve pressure after a collision.
+const COLLISION_REVERSE_DURATION: Duration = Duration::from_millis(300);
 
 /// The standing desk implementation.
 #[derive(Debug)]
-pub(crate) struct StandingDesk<S: DistanceSensor = HCSR04, M: Motor = DeskMotor> {
+pub(crate) struct StandingDesk<
+    S: DistanceSensor = HCSR04,
+    M: Motor = DeskMotor,
+    C: CollisionSensor = Lis3dh,
+> {
     config: TableConfig,
-    sensor: S,
+    sensor: Arc<Mutex<S>>,
     motor: M,
+    // `None` when the anti-collision accelerometer is disabled in config.
+    collision_sensor: Option<C>,
+    // `None` when the background sampling thread is disabled in config, in
+    // which case movement loops fall back to a synchronous measurement.
+    background_sampler: Option<BackgroundSampler>,
 }
 
 impl StandingDesk {
     /// Creates a new instance of a standing desk.
     pub fn new(config: Config) -> Self {
-        let sensor = HCSR04::new(config.sensor);
+        let sensor = Arc::new(Mutex::new(HCSR04::new(config.sensor)));
         let motor = DeskMotor::new(config.motor);
+        let collision_sensor = if config.collision.enabled {
+            match Lis3dh::new(config.collision) {
+                Ok(sensor) => Some(sensor),
+                Err(error) => {
+                    warn!("Collision sensor enabled but failed to initialize: {error}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let background_sampler = if config.sampling.enabled {
+            Some(BackgroundSampler::spawn(
+                Arc::clone(&sensor),
+                config.sampling.buffer_depth,
+                config.sampling.sample_rate_hz,
+            ))
+        } else {
+            None
+        };
         Self {
             config: config.table,
             sensor,
             motor,
+            collision_sensor,
+            background_sampler,
         }
     }
 }
 
-impl<S: DistanceSensor, M: Motor> Movement for StandingDesk<S, M> {
+impl<S: DistanceSensor, M: Motor, C: CollisionSensor> StandingDesk<S, M, C> {
+    /// Checks the collision sensor, if any is configured, and reacts to a
+    /// shock event by stopping the motor, reversing briefly to relieve
+    /// pressure, and returning an error.
+    ///
+    /// `reverse` should move the motor opposite to the direction that was
+    /// running when the shock was detected.
+    fn check_for_collision(
+        &mut self,
+        reverse: impl FnOnce(&mut M),
+    ) -> Result<()> {
+        let Some(collision_sensor) = self.collision_sensor.as_mut() else {
+            return Ok(());
+        };
+        if !collision_sensor.poll_shock()? {
+            return Ok(());
+        }
+        info!("Collision detected, stopping and relieving pressure");
+        self.motor.stop();
+        reverse(&mut self.motor);
+        sleep(COLLISION_REVERSE_DURATION);
+        self.motor.stop();
+        Err(anyhow!("movement aborted, collision sensor detected a shock"))
+    }
+
+    /// Returns the current height, preferring the most recent reading from
+    /// the background sampling thread's ring buffer over a fresh,
+    /// synchronous measurement for tighter stop accuracy. Falls back to a
+    /// synchronous measurement when the thread isn't running or hasn't
+    /// produced a first sample yet.
+    fn read_height(&mut self) -> Result<Centimeter> {
+        if let Some(height) = self
+            .background_sampler
+            .as_ref()
+            .and_then(BackgroundSampler::latest_height)
+        {
+            return Ok(height);
+        }
+        self.sensor
+            .lock()
+            .expect("sensor mutex must not be poisoned")
+            .current_height()
+    }
+
+    /// Creates a [StallGuard] for a single motor move, configured from
+    /// [TableConfig].
+    fn new_stall_guard(&self) -> StallGuard {
+        StallGuard::new(
+            self.config.stall_window_size,
+            self.config.stall_min_progress_cm,
+            self.config.stall_timeout,
+            self.config.move_timeout,
+        )
+    }
+}
+
+impl<M: Motor, C: CollisionSensor> StandingDesk<HCSR04, M, C> {
+    /// Refreshes the ambient temperature the sensor uses for its
+    /// speed-of-sound distance model, letting a caller (e.g. a room sensor
+    /// polling loop) update it without a full recalibration.
+    pub fn set_ambient_temperature(
+        &mut self,
+        temperature_celsius: f32,
+    ) {
+        self.sensor
+            .lock()
+            .expect("sensor mutex must not be poisoned")
+            .set_ambient_temperature(temperature_celsius);
+    }
+
+    /// Refreshes the ambient relative humidity used to refine the
+    /// speed-of-sound distance model.
+    pub fn set_ambient_relative_humidity(
+        &mut self,
+        relative_humidity_percent: f32,
+    ) {
+        self.sensor
+            .lock()
+            .expect("sensor mutex must not be poisoned")
+            .set_ambient_relative_humidity(relative_humidity_percent);
+    }
+}
+
+impl<S: DistanceSensor, M: Motor, C: CollisionSensor> Movement for StandingDesk<S, M, C> {
     fn move_to_standing(&mut self) -> Result<()> {
         info!("Moving to standing position ...");
         self.move_to_height(self.config.standing_height)
@@ -50,31 +174,47 @@ impl<S: DistanceSensor, M: Motor> Movement for StandingDesk<S, M> {
     fn calibrate(&mut self) -> Result<()> {
         info!("Calibrating");
         self.motor.up();
-        let mut current_height = self.sensor.get_current_height()?;
+        let mut current_height = self.read_height()?;
         // We subtract a bit to kick-start the while loop below
         let mut previous_height = current_height - Centimeter(1);
-        // TODO add timeout
+        let mut stall_guard = self.new_stall_guard();
         while previous_height < current_height {
+            self.check_for_collision(|motor| motor.down())?;
+            if let Err(error) = stall_guard.record(current_height) {
+                self.motor.stop();
+                return Err(error);
+            }
             // Table is still moving
             sleep(Duration::from_millis(200));
             previous_height = current_height;
-            current_height = self.sensor.get_current_height()?;
+            current_height = self.read_height()?;
         }
         self.motor.stop();
-        self.sensor.set_max_height(self.config.max_table_height)?;
+        self.sensor
+            .lock()
+            .expect("sensor mutex must not be poisoned")
+            .set_max_height(self.config.max_table_height)?;
 
         self.motor.down();
-        // TODO add timeout
         // We add a bit to kick-start the while loop below
         previous_height = current_height + Centimeter(1);
+        let mut stall_guard = self.new_stall_guard();
         while previous_height > current_height {
+            self.check_for_collision(|motor| motor.up())?;
+            if let Err(error) = stall_guard.record(current_height) {
+                self.motor.stop();
+                return Err(error);
+            }
             // Table is still moving down
             sleep(Duration::from_millis(200));
             previous_height = current_height;
-            current_height = self.sensor.get_current_height()?;
+            current_height = self.read_height()?;
         }
         self.motor.stop();
-        self.sensor.set_min_height(self.config.min_table_height)?;
+        self.sensor
+            .lock()
+            .expect("sensor mutex must not be poisoned")
+            .set_min_height(self.config.min_table_height)?;
 
         // TODO save calibration data to file
 
@@ -97,7 +237,7 @@ impl<S: DistanceSensor, M: Motor> Movement for StandingDesk<S, M> {
             ));
         }
         info!("Moving to height {height_cm:?}");
-        let current_height = self.sensor.get_current_height()?;
+        let current_height = self.read_height()?;
         // We allow for some tolerance as moving the table is not so precise
         if height_cm - Centimeter(1) <= current_height
             && current_height <= height_cm + Centimeter(1)
@@ -105,18 +245,36 @@ impl<S: DistanceSensor, M: Motor> Movement for StandingDesk<S, M> {
             debug!("Table already at desired height");
             return Ok(());
         }
-        // TODO add timeout
         if current_height < height_cm {
             self.motor.up();
-            while self.sensor.get_current_height()? < height_cm {
+            let mut stall_guard = self.new_stall_guard();
+            loop {
+                let height = self.read_height()?;
+                if height >= height_cm {
+                    break;
+                }
+                self.check_for_collision(|motor| motor.down())?;
+                if let Err(error) = stall_guard.record(height) {
+                    self.motor.stop();
+                    return Err(error);
+                }
                 sleep(Duration::from_millis(200));
             }
             self.motor.stop();
         }
-        // TODO add timeout
         if current_height > height_cm {
             self.motor.down();
-            while self.sensor.get_current_height()? > height_cm {
+            let mut stall_guard = self.new_stall_guard();
+            loop {
+                let height = self.read_height()?;
+                if height <= height_cm {
+                    break;
+                }
+                self.check_for_collision(|motor| motor.up())?;
+                if let Err(error) = stall_guard.record(height) {
+                    self.motor.stop();
+                    return Err(error);
+                }
                 sleep(Duration::from_millis(200));
             }
             self.motor.stop();